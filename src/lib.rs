@@ -0,0 +1,562 @@
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::hash::Hash;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Operator {
+    ADD,
+    SUBTRACT,
+    MULTIPLY,
+    DIVIDE,
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Operator::ADD => write!(f, "+"),
+            Operator::SUBTRACT => write!(f, "-"),
+            Operator::MULTIPLY => write!(f, "*"),
+            Operator::DIVIDE => write!(f, "/"),
+        }
+    }
+}
+
+impl Operator {
+    pub fn values() -> Vec<Operator> {
+        vec![
+            Operator::ADD,
+            Operator::SUBTRACT,
+            Operator::DIVIDE,
+            Operator::MULTIPLY,
+        ]
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::ADD | Operator::SUBTRACT => 1,
+            Operator::MULTIPLY | Operator::DIVIDE => 2,
+        }
+    }
+
+    fn is_commutative(&self) -> bool {
+        matches!(self, Operator::ADD | Operator::MULTIPLY)
+    }
+}
+
+// An exact fraction, always kept in lowest terms with a positive denominator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rational {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Rational {
+    fn new(numerator: i64, denominator: i64) -> Rational {
+        let sign: i64 = if denominator < 0 { -1 } else { 1 };
+        let divisor = gcd(numerator.unsigned_abs(), denominator.unsigned_abs()).max(1) as i64;
+        Rational {
+            numerator: sign * numerator / divisor,
+            denominator: sign * denominator / divisor,
+        }
+    }
+
+    fn integer(value: i32) -> Rational {
+        Rational::new(value as i64, 1)
+    }
+
+    fn is_integer(&self) -> bool {
+        self.denominator == 1
+    }
+
+    fn abs(&self) -> Rational {
+        Rational::new(self.numerator.abs(), self.denominator)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> std::cmp::Ordering {
+        (self.numerator * other.denominator).cmp(&(other.numerator * self.denominator))
+    }
+}
+
+impl std::ops::Add for Rational {
+    type Output = Rational;
+    fn add(self, other: Rational) -> Rational {
+        Rational::new(
+            self.numerator * other.denominator + other.numerator * self.denominator,
+            self.denominator * other.denominator,
+        )
+    }
+}
+
+impl std::ops::Sub for Rational {
+    type Output = Rational;
+    fn sub(self, other: Rational) -> Rational {
+        Rational::new(
+            self.numerator * other.denominator - other.numerator * self.denominator,
+            self.denominator * other.denominator,
+        )
+    }
+}
+
+impl std::ops::Mul for Rational {
+    type Output = Rational;
+    fn mul(self, other: Rational) -> Rational {
+        Rational::new(self.numerator * other.numerator, self.denominator * other.denominator)
+    }
+}
+
+impl std::ops::Div for Rational {
+    type Output = Rational;
+    fn div(self, other: Rational) -> Rational {
+        Rational::new(self.numerator * other.denominator, self.denominator * other.numerator)
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[derive(Clone)]
+pub enum Operation<T> {
+    Leaf(T),
+    Node(Box<Operation<T>>, Operator, Box<Operation<T>>),
+}
+
+impl<T: Display> Display for Operation<T> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Operation::Leaf(value) => write!(f, "{}", value),
+            Operation::Node(left, operator, right) => {
+                write_operand(f, left, operator, false)?;
+                write!(f, " {} ", operator)?;
+                write_operand(f, right, operator, true)
+            }
+        }
+    }
+}
+
+// Writes `operand`, parenthesizing it if its top-level operator binds less
+// tightly than `parent_operator`, or binds equally but would silently change
+// meaning if left unparenthesized on the right of a non-associative operator
+// (e.g. `a - (b - c)` must not print as `a - b - c`).
+fn write_operand<T: Display>(
+    f: &mut Formatter,
+    operand: &Operation<T>,
+    parent_operator: &Operator,
+    is_right_operand: bool,
+) -> std::fmt::Result {
+    if needs_parens(operand, parent_operator, is_right_operand) {
+        write!(f, "({})", operand)
+    } else {
+        write!(f, "{}", operand)
+    }
+}
+
+fn needs_parens<T>(operand: &Operation<T>, parent_operator: &Operator, is_right_operand: bool) -> bool {
+    match operand {
+        Operation::Leaf(_) => false,
+        Operation::Node(_, operator, _) => {
+            let operand_precedence = operator.precedence();
+            let parent_precedence = parent_operator.precedence();
+            operand_precedence < parent_precedence
+                || (operand_precedence == parent_precedence
+                    && is_right_operand
+                    && matches!(parent_operator, Operator::SUBTRACT | Operator::DIVIDE))
+        }
+    }
+}
+
+impl Operation<i32> {
+    // Evaluates the tree using exact rational arithmetic, so e.g. `7 / 2`
+    // yields `7/2` rather than being truncated to a bogus `3`. Division by
+    // zero always prunes the subtree to `None`. When `exact_division` is
+    // set, Countdown's legal-move rules are enforced as well: every division
+    // must have zero remainder and every intermediate result must stay a
+    // positive integer, so any subtree that would only be valid as a
+    // fraction or go to zero/negative is pruned instead of carried forward.
+    pub fn evaluate(&self, exact_division: bool) -> Option<Rational> {
+        match self {
+            Operation::Leaf(value) => Some(Rational::integer(*value)),
+            Operation::Node(left, operator, right) => {
+                let left = left.evaluate(exact_division)?;
+                let right = right.evaluate(exact_division)?;
+                let result = match operator {
+                    Operator::ADD => left + right,
+                    Operator::SUBTRACT => left - right,
+                    Operator::MULTIPLY => left * right,
+                    Operator::DIVIDE => {
+                        if right.numerator == 0 {
+                            return None;
+                        }
+                        let quotient = left / right;
+                        if exact_division && !quotient.is_integer() {
+                            return None;
+                        }
+                        quotient
+                    }
+                };
+                if exact_division && result.numerator <= 0 {
+                    None
+                } else {
+                    Some(result)
+                }
+            }
+        }
+    }
+}
+
+// The full, unfiltered stream of candidate expressions over `operands`.
+pub fn all_operations(operands: Vec<i32>) -> impl Iterator<Item = Operation<i32>> {
+    power_set(operands)
+        .flat_map(permutations)
+        .flat_map(generate_operations)
+}
+
+// A generous default expression length when `--with-replacement` is given
+// without a repeat limit, so unbounded reuse can still lengthen expressions
+// past the number of supplied values.
+const DEFAULT_MAX_ARITY_WITH_REPLACEMENT: usize = 6;
+
+// Like `all_operations`, but each supplied value may appear more than once:
+// every ordered sequence drawn from `operands` with repetition is fed into
+// `generate_operations`, capping how many times any single supplied value
+// may recur at `max_repeats` (unbounded if `None`, with the expression length
+// then capped at the larger of `operands.len()` and
+// `DEFAULT_MAX_ARITY_WITH_REPLACEMENT`). The expression length is capped at `operands.len() * max_repeats`
+// when bounded, so e.g. a single value with `max_repeats == 2` can still reach
+// length 2 (`5 + 5`) instead of being capped at length 1.
+pub fn all_operations_with_replacement(
+    operands: Vec<i32>,
+    max_repeats: Option<usize>,
+) -> impl Iterator<Item = Operation<i32>> {
+    let max_len = match max_repeats {
+        Some(max_repeats) => operands.len() * max_repeats,
+        None => operands.len().max(DEFAULT_MAX_ARITY_WITH_REPLACEMENT),
+    };
+    sequences_with_replacement(operands, max_len, max_repeats)
+        .into_iter()
+        .flat_map(generate_operations)
+}
+
+fn sequences_with_replacement<T: Clone>(
+    values: Vec<T>,
+    max_len: usize,
+    max_repeats: Option<usize>,
+) -> Vec<Vec<T>> {
+    let mut result = Vec::new();
+    let mut counts = vec![0usize; values.len()];
+    let mut current = Vec::new();
+    sequences_with_replacement_helper(
+        &values,
+        max_len,
+        max_repeats,
+        &mut counts,
+        &mut current,
+        &mut result,
+    );
+    result
+}
+
+fn sequences_with_replacement_helper<T: Clone>(
+    values: &[T],
+    max_len: usize,
+    max_repeats: Option<usize>,
+    counts: &mut Vec<usize>,
+    current: &mut Vec<T>,
+    result: &mut Vec<Vec<T>>,
+) {
+    if !current.is_empty() {
+        result.push(current.clone());
+    }
+    if current.len() == max_len {
+        return;
+    }
+    for i in 0..values.len() {
+        if max_repeats.is_some_and(|max_repeats| counts[i] >= max_repeats) {
+            continue;
+        }
+        counts[i] += 1;
+        current.push(values[i].clone());
+        sequences_with_replacement_helper(values, max_len, max_repeats, counts, current, result);
+        current.pop();
+        counts[i] -= 1;
+    }
+}
+
+pub fn filter_operations_for_value(
+    operations: impl Iterator<Item = Operation<i32>>,
+    target_value: i32,
+    exact_division: bool,
+) -> impl Iterator<Item = Operation<i32>> {
+    let target = Rational::integer(target_value);
+    operations.filter(move |x| x.evaluate(exact_division) == Some(target))
+}
+
+// Convenience entry point combining `all_operations` with `filter_operations_for_value`.
+pub fn find_operations_for_value(
+    operands: Vec<i32>,
+    target_value: i32,
+    exact_division: bool,
+) -> impl Iterator<Item = Operation<i32>> {
+    filter_operations_for_value(all_operations(operands), target_value, exact_division)
+}
+
+// Keeps the `n` operations with values closest to `target_value`, using a
+// max-heap of bounded size `n` keyed by distance: every candidate is pushed,
+// and whenever the heap grows past `n` the current worst (largest distance)
+// entry is popped. This keeps memory at O(n) and runs in O(total * log n)
+// instead of collecting and sorting every candidate. The survivors are
+// returned sorted ascending by distance.
+pub fn find_closest_operations(
+    operations: impl Iterator<Item = Operation<i32>>,
+    target_value: i32,
+    exact_division: bool,
+    n: usize,
+) -> Vec<Operation<i32>> {
+    let target = Rational::integer(target_value);
+    let mut heap: BinaryHeap<ClosestOperation<i32>> = BinaryHeap::new();
+    for operation in operations {
+        if let Some(value) = operation.evaluate(exact_division) {
+            let distance = (value - target).abs();
+            heap.push(ClosestOperation { distance, operation });
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+    }
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|entry| entry.operation)
+        .collect()
+}
+
+// Wraps an operation with its distance from the target so a `BinaryHeap` can
+// order candidates by closeness alone.
+struct ClosestOperation<T> {
+    distance: Rational,
+    operation: Operation<T>,
+}
+
+impl<T> PartialEq for ClosestOperation<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance
+    }
+}
+
+impl<T> Eq for ClosestOperation<T> {}
+
+impl<T> PartialOrd for ClosestOperation<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for ClosestOperation<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.distance.cmp(&other.distance)
+    }
+}
+
+// Enumerates every binary-tree shape over the ordered slice `operands`: for
+// each split point `k`, combine every left tree over `operands[..k]` with
+// every right tree over `operands[k..]` under every operator. The number of
+// shapes follows the Catalan numbers, and sub-slices recur across different
+// split points, so results are memoized by `(start, end)`.
+pub fn generate_operations<T: 'static + Clone>(operands: Vec<T>) -> Box<dyn Iterator<Item = Operation<T>>> {
+    let mut memo: HashMap<(usize, usize), Vec<Operation<T>>> = HashMap::new();
+    let len = operands.len();
+    Box::new(generate_operations_memo(&operands, 0, len, &mut memo).into_iter())
+}
+
+fn generate_operations_memo<T: Clone>(
+    operands: &[T],
+    start: usize,
+    end: usize,
+    memo: &mut HashMap<(usize, usize), Vec<Operation<T>>>,
+) -> Vec<Operation<T>> {
+    if let Some(cached) = memo.get(&(start, end)) {
+        return cached.clone();
+    }
+    let result = if end - start == 1 {
+        vec![Operation::Leaf(operands[start].clone())]
+    } else {
+        let mut result = Vec::new();
+        for split in (start + 1)..end {
+            let left_trees = generate_operations_memo(operands, start, split, memo);
+            let right_trees = generate_operations_memo(operands, split, end, memo);
+            for left in &left_trees {
+                for right in &right_trees {
+                    for operator in Operator::values() {
+                        result.push(Operation::Node(
+                            Box::new(left.clone()),
+                            operator,
+                            Box::new(right.clone()),
+                        ));
+                    }
+                }
+            }
+        }
+        result
+    };
+    memo.insert((start, end), result.clone());
+    result
+}
+
+pub fn power_set<T: 'static + Clone>(vec: Vec<T>) -> impl Iterator<Item = Vec<T>> {
+    if vec.len() >= 32 {
+        panic!("Set is too large to generate power sets for.");
+    }
+    let base: i32 = 2;
+    (0..(base.pow(vec.len() as u32))).map(move |bit_vector: i32| {
+        vec.clone()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _value)| (1 << index) & bit_vector != 0)
+            .map(|(_index, value)| value)
+            .collect::<Vec<T>>()
+    })
+}
+
+pub fn permutations<T: 'static + Clone + Debug>(vec: Vec<T>) -> Box<dyn Iterator<Item = Vec<T>>> {
+    if vec.len() == 1 {
+        Box::new(vec![vec].into_iter())
+    } else {
+        Box::new((0..vec.len()).flat_map(move |i| {
+            let mut vec_without_i = vec.clone();
+            let removed_element: T = vec_without_i.remove(i);
+            permutations(vec_without_i).map(move |mut permutation| {
+                permutation.push(removed_element.clone());
+                permutation
+            })
+        }))
+    }
+}
+
+pub fn process_associative_operation_filter<T: Clone + Ord + Hash + Display>(
+    operations: impl Iterator<Item = Operation<T>>,
+) -> impl Iterator<Item = Operation<T>> {
+    let mut deduped: InsertionOrderedMap<CanonicalOperation<T>, Operation<T>> = InsertionOrderedMap::new();
+    for operation in operations {
+        let key = CanonicalOperation::from(&operation);
+        deduped.insert_if_absent(key, operation);
+    }
+    // `CanonicalOperation` only folds the commutative operators `+`/`*` into
+    // order-independent chains; `-`/`/` stay ordered `Node`s, so e.g.
+    // `(5*3+3)-5` and `5*3+(3-5)` (always equal, and rendered identically)
+    // still get distinct canonical keys. Catch those residual duplicates
+    // with a final pass keyed on the rendered string.
+    let mut deduped_by_display: InsertionOrderedMap<String, Operation<T>> = InsertionOrderedMap::new();
+    for operation in deduped.into_values() {
+        let key = format!("{}", operation);
+        deduped_by_display.insert_if_absent(key, operation);
+    }
+    deduped_by_display.into_values()
+}
+
+// Iterates in first-insertion order, so deduplication produces stable output
+// across runs instead of `HashMap`'s randomized iteration order.
+struct InsertionOrderedMap<K, V> {
+    order: Vec<K>,
+    values: HashMap<K, V>,
+}
+
+impl<K: Hash + Eq + Clone, V> InsertionOrderedMap<K, V> {
+    fn new() -> Self {
+        InsertionOrderedMap {
+            order: Vec::new(),
+            values: HashMap::new(),
+        }
+    }
+
+    // Inserts `value` under `key` only if `key` has not been seen before.
+    fn insert_if_absent(&mut self, key: K, value: V) {
+        if !self.values.contains_key(&key) {
+            self.order.push(key.clone());
+            self.values.insert(key, value);
+        }
+    }
+
+    fn into_values(self) -> impl Iterator<Item = V> {
+        let InsertionOrderedMap { order, mut values } = self;
+        order.into_iter().map(move |key| values.remove(&key).unwrap())
+    }
+}
+
+// A canonical form of an `Operation` tree used to deduplicate "similar"
+// operations: two trees are similar if they use the same operands and
+// operators, up to reordering and reparenthesizing commutative-associative
+// operators (`+`, `*`). A maximal chain of the same commutative operator
+// (regardless of how it was parenthesized) canonicalizes to a `Chain` of its
+// leaf-level terms sorted into a stable order, so `(3 + 5) + 5` and
+// `3 + (5 + 5)` collapse to the same key. Operand multiplicity is preserved
+// (a sorted `Vec`, not a `HashSet`), so e.g. `5 + 5 + 3` and `5 + 3` still
+// canonicalize differently. `-`/`/` are neither commutative nor associative,
+// so their children stay an ordered `Node` pair.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum CanonicalOperation<T> {
+    Leaf(T),
+    Node(Box<CanonicalOperation<T>>, Operator, Box<CanonicalOperation<T>>),
+    Chain(Operator, Vec<CanonicalOperation<T>>),
+}
+
+impl<T: Clone + Ord> From<&Operation<T>> for CanonicalOperation<T> {
+    fn from(operation: &Operation<T>) -> Self {
+        match operation {
+            Operation::Leaf(value) => CanonicalOperation::Leaf(value.clone()),
+            Operation::Node(_, operator, _) if operator.is_commutative() => {
+                let mut terms = Vec::new();
+                flatten_chain(operation, operator, &mut terms);
+                terms.sort();
+                CanonicalOperation::Chain(operator.clone(), terms)
+            }
+            Operation::Node(left, operator, right) => CanonicalOperation::Node(
+                Box::new(CanonicalOperation::from(left.as_ref())),
+                operator.clone(),
+                Box::new(CanonicalOperation::from(right.as_ref())),
+            ),
+        }
+    }
+}
+
+// Collects the leaf-level terms of the maximal `operator`-chain rooted at
+// `operation`: descends through nested nodes that share `operator`, and
+// canonicalizes everything else (a different operator, or a leaf) as a
+// single term. This is what lets any parenthesization of the same chain of
+// `+` or `*` collapse to the same sorted `terms` list.
+fn flatten_chain<T: Clone + Ord>(
+    operation: &Operation<T>,
+    operator: &Operator,
+    terms: &mut Vec<CanonicalOperation<T>>,
+) {
+    match operation {
+        Operation::Node(left, op, right) if op == operator => {
+            flatten_chain(left, operator, terms);
+            flatten_chain(right, operator, terms);
+        }
+        other => terms.push(CanonicalOperation::from(other)),
+    }
+}
@@ -1,12 +1,13 @@
+extern crate chance;
 extern crate clap;
 
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::fmt::Debug;
+use chance::all_operations;
+use chance::all_operations_with_replacement;
+use chance::filter_operations_for_value;
+use chance::find_closest_operations;
+use chance::process_associative_operation_filter;
+use chance::Operation;
 use std::fmt::Display;
-use std::fmt::Formatter;
-use std::hash::Hash;
-use std::hash::Hasher;
 
 fn main() {
     let matches = clap::App::new("chance")
@@ -31,6 +32,24 @@ fn main() {
                 .long("enable_associative_operation_filter")
                 .help("True to filter out similar operations (operations that use the same numbers and operators but in a different order)"),
         )
+        .arg(
+            clap::Arg::with_name("exact_division")
+                .long("exact-division")
+                .help("True to require every division to be exact and every intermediate result to stay a positive integer, matching the real numbers-game rules"),
+        )
+        .arg(
+            clap::Arg::with_name("closest")
+                .long("closest")
+                .help("Instead of requiring an exact match, show the N operations whose value is closest to the target")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("with_replacement")
+                .long("with-replacement")
+                .help("Allow each supplied value to be reused up to K times across an expression (unbounded, up to the expression length, if K is omitted), instead of treating --values as used at most once")
+                .takes_value(true)
+                .min_values(0),
+        )
         .get_matches();
     let values: Vec<i32> = matches
         .value_of("values")
@@ -43,14 +62,34 @@ fn main() {
         .expect("Requires --target")
         .parse()
         .expect("Target must be an integer");
-    let operations = find_operations_for_value(values, target);
-    if matches.occurrences_of("enable_associative_operation_filter") > 0 {
+    let exact_division = matches.occurrences_of("exact_division") > 0;
+    let operations: Box<dyn Iterator<Item = Operation<i32>>> =
+        if matches.occurrences_of("with_replacement") > 0 {
+            let max_repeats = matches
+                .value_of("with_replacement")
+                .map(|k| k.parse::<usize>().expect("--with-replacement value must be an integer"));
+            Box::new(all_operations_with_replacement(values, max_repeats))
+        } else {
+            Box::new(all_operations(values))
+        };
+    let operations: Box<dyn Iterator<Item = Operation<i32>>> =
+        if matches.occurrences_of("enable_associative_operation_filter") > 0 {
+            Box::new(process_associative_operation_filter(operations))
+        } else {
+            Box::new(operations)
+        };
+    if let Some(closest) = matches.value_of("closest") {
+        let n = closest.parse::<usize>().expect("--closest must be an integer");
+        let closest_operations = find_closest_operations(operations, target, exact_division, n);
         println!(
             "{}",
-            format_operations(process_associative_operation_filter(operations))
+            format_closest_operations(closest_operations, target, exact_division)
         );
     } else {
-        println!("{}", format_operations(operations));
+        println!(
+            "{}",
+            format_operations(filter_operations_for_value(operations, target, exact_division))
+        );
     }
 }
 
@@ -62,181 +101,19 @@ fn format_operations<T: Display>(operations: impl Iterator<Item = Operation<T>>)
         .join("\n")
 }
 
-fn process_associative_operation_filter<T: Hash + Eq + Clone>(
-    operations: impl Iterator<Item = Operation<T>>,
-) -> impl Iterator<Item = Operation<T>> {
-    operations
-        .map(|operation| (SimilarOperationKey::from(operation.clone()), operation))
-        .collect::<HashMap<SimilarOperationKey<T>, Operation<T>>>()
-        .into_iter()
-        .map(|(_key, operation)| operation)
-}
-
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-enum Operator {
-    ADD,
-    SUBTRACT,
-    MULTIPLY,
-    DIVIDE,
-}
-
-impl Display for Operator {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        match self {
-            Operator::ADD => write!(f, "+"),
-            Operator::SUBTRACT => write!(f, "-"),
-            Operator::MULTIPLY => write!(f, "*"),
-            Operator::DIVIDE => write!(f, "/"),
-        }
-    }
-}
-
-impl Operator {
-    fn values() -> Vec<Operator> {
-        return vec![
-            Operator::ADD,
-            Operator::SUBTRACT,
-            Operator::DIVIDE,
-            Operator::MULTIPLY,
-        ];
-    }
-}
-
-#[derive(Clone)]
-enum Operation<T> {
-    SingleOperand(T),
-    Operation(T, Operator, Box<Operation<T>>),
-}
-
-impl<T: Display> Display for Operation<T> {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        match self {
-            Operation::SingleOperand(value) => write!(f, "{}", value),
-            Operation::Operation(operand1, operator, operand2) => {
-                write!(f, "{} {} {}", operand1, operator, operand2)
-            }
-        }
-    }
-}
-
-impl Operation<i32> {
-    fn evaluate(&self) -> i32 {
-        match self {
-            Operation::SingleOperand(value) => value.clone(),
-            Operation::Operation(operand1, operator, operand2) => {
-                let operand2 = operand2.evaluate();
-                match operator {
-                    Operator::ADD => operand1 + operand2,
-                    Operator::SUBTRACT => operand1 - operand2,
-                    Operator::DIVIDE => {
-                        if operand2 == 0 {
-                            0
-                        } else {
-                            operand1 / operand2
-                        }
-                    }
-                    Operator::MULTIPLY => operand1 * operand2,
-                }
-            }
-        }
-    }
-}
-
-fn find_operations_for_value(
-    operands: Vec<i32>,
+fn format_closest_operations(
+    operations: Vec<Operation<i32>>,
     target_value: i32,
-) -> impl Iterator<Item = Operation<i32>> {
-    power_set(operands)
-        .flat_map(|sets| permutations(sets))
+    exact_division: bool,
+) -> String {
+    operations
         .into_iter()
-        .flat_map(|operands| generate_operations(operands))
-        .filter(move |x| x.evaluate() == target_value)
-}
-
-fn generate_operations<T: 'static + Clone + Debug>(
-    mut operands: Vec<T>,
-) -> Box<Iterator<Item = Operation<T>>> {
-    let first_operand = operands.remove(0);
-    // Add one because we just removed a value.
-    if operands.len() + 1 == 1 {
-        Box::new(vec![Operation::SingleOperand(first_operand)].into_iter())
-    } else {
-        let sub_operations: Box<Iterator<Item = Operation<T>>> = generate_operations(operands);
-        Box::new(sub_operations.flat_map(move |sub_operation| {
-            let first_operand = first_operand.clone();
-            Operator::values().into_iter().map(move |operator| {
-                Operation::Operation(
-                    first_operand.clone(),
-                    operator.clone(),
-                    Box::new(sub_operation.clone()),
-                )
-            })
-        }))
-    }
-}
-
-fn power_set<T: 'static + Clone>(vec: Vec<T>) -> impl Iterator<Item = Vec<T>> {
-    if vec.len() >= 32 {
-        panic!("Set is too large to generate power sets for.");
-    }
-    let base: i32 = 2;
-    (0..(base.pow(vec.len() as u32))).map(move |bit_vector: i32| {
-        vec.clone()
-            .into_iter()
-            .enumerate()
-            .filter(|(index, _value)| (1 << index) & bit_vector != 0)
-            .map(|(_index, value)| value)
-            .collect::<Vec<T>>()
-    })
-}
-
-fn permutations<T: 'static + Clone + Debug>(vec: Vec<T>) -> Box<Iterator<Item = Vec<T>>> {
-    if vec.len() == 1 {
-        Box::new(vec![vec].into_iter())
-    } else {
-        Box::new((0..vec.len()).flat_map(move |i| {
-            let mut vec_without_i = vec.clone();
-            let removed_element: T = vec_without_i.remove(i);
-            permutations(vec_without_i).map(move |mut permutation| {
-                permutation.push(removed_element.clone());
-                permutation
-            })
-        }))
-    }
-}
-
-// A key for a hash map that helps to deduplicate "similar" operations.
-// Operations are considered similar if they contain the exact same operands and operators but in different orders.
-#[derive(PartialEq, Eq)]
-struct SimilarOperationKey<T: Hash + Eq> {
-    operators: HashSet<Operator>,
-    operands: HashSet<T>,
-}
-
-impl<T: Hash + Eq> Hash for SimilarOperationKey<T> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        for operator in self.operators.iter() {
-            operator.hash(state);
-        }
-        for operand in self.operands.iter() {
-            operand.hash(state);
-        }
-    }
-}
-
-impl<T: Hash + Eq> From<Operation<T>> for SimilarOperationKey<T> {
-    fn from(operation: Operation<T>) -> Self {
-        match operation {
-            Operation::SingleOperand(value) => SimilarOperationKey {
-                operators: HashSet::new(),
-                operands: vec![value].into_iter().collect(),
-            },
-            Operation::Operation(operand1, operator, operand2) => {
-                let mut key: SimilarOperationKey<T> = (*operand2).into();
-                key.operators.insert(operator);
-                key.operands.insert(operand1);
-                key
-            }
-        }
-    }
+        .map(|operation| {
+            let value = operation
+                .evaluate(exact_division)
+                .expect("closest operations always evaluate successfully");
+            format!("{} = {} (target {})", operation, value, target_value)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
 }